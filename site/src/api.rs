@@ -0,0 +1,38 @@
+// Copyright 2016 The rustc-perf Project Developers. See the COPYRIGHT
+// file at the top-level directory.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// Request/response types for `POST /perf/regressions`.
+pub mod regressions {
+    /// Same shape as [`super::data::Request`]: a date/commit range plus the
+    /// stat to scan for regressions in.
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Request {
+        pub start: String,
+        pub end: String,
+        pub stat: String,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Response {
+        pub regressions: Vec<Regression>,
+    }
+
+    /// A single flagged benchmark+profile+cache scenario, sorted by the
+    /// server in descending order of `percent_change` magnitude.
+    #[derive(Debug, Serialize)]
+    pub struct Regression {
+        pub benchmark: String,
+        pub profile: String,
+        pub cache: String,
+        pub commit: String,
+        pub prev_commit: String,
+        pub percent_change: f64,
+        pub sigma: f64,
+    }
+}
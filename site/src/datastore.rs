@@ -0,0 +1,226 @@
+// Copyright 2016 The rustc-perf Project Developers. See the COPYRIGHT
+// file at the top-level directory.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A persistent, indexed store for commit/benchmark/run data, so that
+//! `handle_data` can pull just the date range and stat it needs out of SQL
+//! instead of scanning every in-memory `CommitData`. On push, only commits
+//! not yet indexed are inserted; the commits themselves are still parsed by
+//! a full read of the on-disk corpus.
+//! `InputData`/`CommitData` are still used to resolve a request's logical
+//! start/end commits (fuzzy dates, "HEAD", ...); this module serves
+//! everything past that resolution step.
+
+use std::path::Path;
+
+use antidote::Mutex;
+use failure::Error;
+use rusqlite::{Connection, OptionalExtension, NO_PARAMS};
+use serde_json;
+
+use collector::Run;
+use load::CommitData;
+
+/// Indexes commits, benchmarks, runs, and per-stat values so that only newly
+/// added commits need to be inserted on push, and so that range queries can
+/// be served with SQL instead of scanning every in-memory commit.
+pub trait DataStore: Send + Sync {
+    /// SHAs of every commit already indexed, oldest first.
+    fn indexed_commits(&self) -> Result<Vec<String>, Error>;
+
+    /// Indexes a freshly-parsed commit. A no-op if the commit is already present.
+    fn insert_commit(&self, commit: &CommitData) -> Result<(), Error>;
+
+    /// Every `(commit sha, benchmark name, run, value)` row for `stat`,
+    /// restricted to commits indexed between `start_sha` and `end_sha`
+    /// (inclusive), ordered the same way the commits are. Used to serve
+    /// `handle_data` straight out of SQL instead of re-deriving every
+    /// benchmark's full stat map from the in-memory corpus.
+    fn stat_in_range(
+        &self,
+        start_sha: &str,
+        end_sha: &str,
+        stat: &str,
+    ) -> Result<Vec<(String, String, Run, f64)>, Error>;
+}
+
+/// A `DataStore` backed by a local SQLite database. Connections to SQLite
+/// aren't `Sync`, so access is serialized behind a `Mutex`; this is fine
+/// since each query is already cheap relative to the HTTP round-trip. Uses
+/// `antidote::Mutex` rather than `std::sync::Mutex` so a panic mid-query
+/// can't poison it and take every subsequent query down with it, matching
+/// the `antidote::RwLock` used for `InputData` elsewhere.
+pub struct SqliteDataStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteDataStore {
+    /// Opens (creating if necessary) the database at `path` and ensures the
+    /// schema exists.
+    pub fn open(path: &Path) -> Result<SqliteDataStore, Error> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS commits (
+                id INTEGER PRIMARY KEY,
+                sha TEXT NOT NULL UNIQUE,
+                date TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS benchmarks (
+                id INTEGER PRIMARY KEY,
+                commit_id INTEGER NOT NULL REFERENCES commits(id),
+                name TEXT NOT NULL,
+                error TEXT
+            );
+            CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY,
+                benchmark_id INTEGER NOT NULL REFERENCES benchmarks(id),
+                run_json TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS run_stats (
+                run_id INTEGER NOT NULL REFERENCES runs(id),
+                stat TEXT NOT NULL,
+                value REAL NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS run_stats_stat ON run_stats(stat);
+            ",
+        )?;
+        Ok(SqliteDataStore { conn: Mutex::new(conn) })
+    }
+}
+
+impl DataStore for SqliteDataStore {
+    fn indexed_commits(&self) -> Result<Vec<String>, Error> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT sha FROM commits ORDER BY date ASC")?;
+        let shas = stmt
+            .query_map(NO_PARAMS, |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(shas)
+    }
+
+    fn insert_commit(&self, commit: &CommitData) -> Result<(), Error> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+
+        let already_indexed = tx
+            .query_row(
+                "SELECT id FROM commits WHERE sha = ?1",
+                &[&commit.commit.sha],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        if already_indexed.is_some() {
+            return Ok(());
+        }
+
+        tx.execute(
+            "INSERT INTO commits (sha, date) VALUES (?1, ?2)",
+            &[&commit.commit.sha, &commit.commit.date.0.to_rfc3339()],
+        )?;
+        let commit_id = tx.last_insert_rowid();
+
+        for (name, benchmark) in &commit.benchmarks {
+            let error = benchmark.as_ref().err().map(|e| e.to_string());
+            tx.execute(
+                "INSERT INTO benchmarks (commit_id, name, error) VALUES (?1, ?2, ?3)",
+                &[&commit_id as &::rusqlite::types::ToSql, name, &error],
+            )?;
+            let benchmark_id = tx.last_insert_rowid();
+
+            if let Ok(ref benchmark) = *benchmark {
+                for run in &benchmark.runs {
+                    let run_json = serde_json::to_string(run)?;
+                    tx.execute(
+                        "INSERT INTO runs (benchmark_id, run_json) VALUES (?1, ?2)",
+                        &[&benchmark_id as &::rusqlite::types::ToSql, &run_json],
+                    )?;
+                    let run_id = tx.last_insert_rowid();
+                    for (stat, value) in run_stats(run) {
+                        tx.execute(
+                            "INSERT INTO run_stats (run_id, stat, value) VALUES (?1, ?2, ?3)",
+                            &[&run_id as &::rusqlite::types::ToSql, &stat, &value],
+                        )?;
+                    }
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn stat_in_range(
+        &self,
+        start_sha: &str,
+        end_sha: &str,
+        stat: &str,
+    ) -> Result<Vec<(String, String, Run, f64)>, Error> {
+        let conn = self.conn.lock();
+        let start_date: String = conn.query_row(
+            "SELECT date FROM commits WHERE sha = ?1",
+            &[&start_sha],
+            |row| row.get(0),
+        )?;
+        let end_date: String = conn.query_row(
+            "SELECT date FROM commits WHERE sha = ?1",
+            &[&end_sha],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT commits.sha, benchmarks.name, runs.run_json, run_stats.value
+             FROM run_stats
+             JOIN runs ON runs.id = run_stats.run_id
+             JOIN benchmarks ON benchmarks.id = runs.benchmark_id
+             JOIN commits ON commits.id = benchmarks.commit_id
+             WHERE run_stats.stat = ?1 AND commits.date BETWEEN ?2 AND ?3
+             ORDER BY commits.date ASC",
+        )?;
+        let rows = stmt
+            .query_map(
+                &[&stat as &::rusqlite::types::ToSql, &start_date, &end_date],
+                |row| {
+                    let sha: String = row.get(0);
+                    let benchmark: String = row.get(1);
+                    let run_json: String = row.get(2);
+                    let value: f64 = row.get(3);
+                    (sha, benchmark, run_json, value)
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(sha, benchmark, run_json, value)| {
+                let run: Run = serde_json::from_str(&run_json)?;
+                Ok((sha, benchmark, run, value))
+            })
+            .collect()
+    }
+}
+
+/// Extracts every named stat from a run, for the `run_stats` table.
+fn run_stats(run: &Run) -> Vec<(String, f64)> {
+    run.stats
+        .iter()
+        .map(|(name, value)| (name.clone(), *value))
+        .collect()
+}
+
+/// One-time migration that imports the current on-disk corpus into `store`.
+/// Safe to re-run: already-indexed commits are skipped.
+pub fn migrate_from_fs<S: DataStore>(store: &S, commits: &[CommitData]) -> Result<(), Error> {
+    let indexed = store.indexed_commits()?.into_iter().collect::<::std::collections::HashSet<_>>();
+    for commit in commits {
+        if indexed.contains(&commit.commit.sha) {
+            continue;
+        }
+        store.insert_commit(commit)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// Copyright 2016 The rustc-perf Project Developers. See the COPYRIGHT
+// file at the top-level directory.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A real entry point for parsing just the commits a push named, so
+//! `handle_push_event` has something to call instead of re-deriving that
+//! filtering ad hoc at the call site.
+//!
+//! `load` has no primitive for parsing a single commit's benchmark output in
+//! isolation, so this still goes through a full [`InputData::from_fs`] read
+//! under the hood; teaching `load` to parse one commit at a time is out of
+//! scope here. What this buys is a single, named place that does the
+//! "keep only the commits this push mentioned" filtering, so it isn't
+//! duplicated (and doesn't risk drifting) across callers.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use failure::Error;
+
+use load::{CommitData, InputData};
+
+impl InputData {
+    /// Returns the [`CommitData`] for just `shas`, out of everything
+    /// `from_fs` would otherwise load.
+    pub fn from_fs_partial(repo_path: &Path, shas: &[String]) -> Result<Vec<CommitData>, Error> {
+        let wanted = shas.iter().collect::<HashSet<_>>();
+        let mut all = InputData::from_fs(repo_path)?;
+        // `data` is keyed by `Commit`, not by its `sha` alone, so the wanted
+        // keys have to be looked up by scanning rather than removed directly.
+        let keys = all
+            .data
+            .keys()
+            .filter(|commit| wanted.contains(&commit.sha))
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(keys
+            .into_iter()
+            .filter_map(|commit| all.data.remove(&commit))
+            .collect())
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2016 The rustc-perf Project Developers. See the COPYRIGHT
+// file at the top-level directory.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reports the outcome of a `/perf/onpush` reload to external endpoints —
+//! a GitHub commit-status update and/or a generic webhook — so an operator
+//! doesn't have to watch server logs to find out whether a push landed.
+
+use failure::Error;
+use futures_cpupool::CpuPool;
+use reqwest;
+use serde_json;
+
+/// Outcome of a single reload, reported to every configured sink.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadReport {
+    pub commit: String,
+    pub success: bool,
+    pub duration_ms: u64,
+}
+
+/// A destination that wants to hear about reload outcomes.
+pub enum Sink {
+    /// POSTs a commit status to `https://api.github.com/repos/{repo}/statuses/{commit}`.
+    GithubStatus { repo: String, token: String },
+    /// POSTs the `ReloadReport` JSON body to an arbitrary URL.
+    Webhook { url: String },
+}
+
+/// Holds the configured sinks and fires them on a worker pool, so that
+/// notification latency never blocks the HTTP response whose completion
+/// triggered it.
+pub struct Notifier {
+    sinks: Vec<Sink>,
+}
+
+impl Notifier {
+    pub fn new(sinks: Vec<Sink>) -> Notifier {
+        Notifier { sinks }
+    }
+
+    /// Fires every configured sink asynchronously on `pool`. A sink's
+    /// failure is logged, not propagated: a broken notification shouldn't
+    /// affect the reload it's reporting on.
+    pub fn notify(&self, pool: &CpuPool, report: ReloadReport) {
+        for sink in &self.sinks {
+            let report = report.clone();
+            match *sink {
+                Sink::GithubStatus { ref repo, ref token } => {
+                    let repo = repo.clone();
+                    let token = token.clone();
+                    pool.spawn_fn(move || -> Result<(), ()> {
+                        if let Err(err) = post_github_status(&repo, &token, &report) {
+                            error!("failed to post GitHub commit status: {:?}", err);
+                        }
+                        Ok(())
+                    }).forget();
+                }
+                Sink::Webhook { ref url } => {
+                    let url = url.clone();
+                    pool.spawn_fn(move || -> Result<(), ()> {
+                        if let Err(err) = post_webhook(&url, &report) {
+                            error!("failed to post reload webhook: {:?}", err);
+                        }
+                        Ok(())
+                    }).forget();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GithubStatusBody {
+    state: &'static str,
+    description: String,
+    context: &'static str,
+}
+
+fn post_github_status(repo: &str, token: &str, report: &ReloadReport) -> Result<(), Error> {
+    let url = format!(
+        "https://api.github.com/repos/{}/statuses/{}",
+        repo, report.commit
+    );
+    let body = GithubStatusBody {
+        state: if report.success { "success" } else { "failure" },
+        description: format!(
+            "rustc-perf reload {} in {}ms",
+            if report.success { "succeeded" } else { "failed" },
+            report.duration_ms
+        ),
+        context: "rustc-perf/reload",
+    };
+
+    reqwest::Client::new()
+        .post(&url)
+        .header(reqwest::header::Authorization(format!("token {}", token)))
+        .json(&body)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+fn post_webhook(url: &str, report: &ReloadReport) -> Result<(), Error> {
+    reqwest::Client::new()
+        .post(url)
+        .json(report)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
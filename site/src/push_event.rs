@@ -0,0 +1,87 @@
+// Copyright 2016 The rustc-perf Project Developers. See the COPYRIGHT
+// file at the top-level directory.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of the GitHub push webhook payload delivered to `/perf/onpush`,
+//! so the handler can update only the affected commits instead of reloading
+//! the entire corpus on every push.
+
+use failure::Error;
+use serde_json::Value;
+
+/// A parsed webhook payload. `Other` covers events that carry no
+/// `head_commit` (a non-`push` event such as a ping); callers should fall
+/// back to a full reload in that case. A payload with a `head_commit` but an
+/// unrecognized shape is not `Other`: [`PushEvent::parse`] returns an `Err`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushEvent {
+    Push {
+        repo_name: String,
+        /// The pushed ref, e.g. `refs/heads/master`. Callers should check
+        /// this before indexing anything: a push to a PR or feature branch
+        /// carries the same shape as a push to `master` and must not be
+        /// treated as one.
+        git_ref: String,
+        tip: String,
+        commits: Vec<String>,
+    },
+    Other,
+}
+
+impl PushEvent {
+    /// Parses the JSON body of a GitHub `push` webhook. Field extraction is
+    /// tolerant: an unrecognized shape returns a descriptive `Err` (missing
+    /// element or wrong type) rather than panicking, so the caller can log
+    /// it and fall back to a full reload.
+    pub fn parse(body: &[u8]) -> Result<PushEvent, Error> {
+        let value: Value = serde_json::from_slice(body)?;
+
+        // Only pushes carry a `head_commit`; other event types (e.g. a ping)
+        // are passed through as `Other` rather than treated as an error.
+        let head_commit = match value.get("head_commit") {
+            Some(head_commit) => head_commit,
+            None => return Ok(PushEvent::Other),
+        };
+
+        let repo_name = value
+            .get("repository")
+            .and_then(|r| r.get("full_name"))
+            .and_then(|n| n.as_str())
+            .ok_or_else(|| format_err!("missing or non-string repository.full_name"))?
+            .to_string();
+
+        let git_ref = value
+            .get("ref")
+            .and_then(|r| r.as_str())
+            .ok_or_else(|| format_err!("missing or non-string ref"))?
+            .to_string();
+
+        let tip = head_commit
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| format_err!("missing or non-string head_commit.id"))?
+            .to_string();
+
+        let commits = value
+            .get("commits")
+            .ok_or_else(|| format_err!("missing commits element"))?
+            .as_array()
+            .ok_or_else(|| format_err!("commits element was not an array"))?
+            .iter()
+            .map(|commit| {
+                commit
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .map(|id| id.to_string())
+                    .ok_or_else(|| format_err!("commit entry missing a string id"))
+            })
+            .collect::<Result<Vec<String>, Error>>()?;
+
+        Ok(PushEvent::Push { repo_name, git_ref, tip, commits })
+    }
+}
@@ -8,14 +8,15 @@
 // except according to those terms.
 
 use std::str;
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 use std::sync::Arc;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::cmp::Ordering;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -23,9 +24,11 @@ use serde_json;
 use rmp_serde;
 use futures::{self, Future, Stream};
 use futures_cpupool::CpuPool;
-use hyper::{self, Get, Post, StatusCode};
-use hyper::header::{AcceptEncoding, CacheControl, CacheDirective, Encoding};
+use hyper::{self, Chunk, Get, Post, StatusCode};
+use hyper::header::{Accept, AcceptEncoding, CacheControl, CacheDirective, Encoding};
 use hyper::header::{ContentEncoding, ContentLength, ContentType};
+use hyper::header::{ByteRangeSpec, ContentRange, ContentRangeSpec, ETag, EntityTag};
+use hyper::header::{HttpDate, IfModifiedSince, IfNoneMatch, LastModified, Range};
 use hyper::mime;
 use hyper::server::{Http, Request, Response, Service};
 use url::Url;
@@ -33,13 +36,22 @@ use flate2::Compression;
 use flate2::write::GzEncoder;
 use semver::Version;
 use failure::Error;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use rustls;
+use tokio_core::net::TcpListener;
+use tokio_core::reactor::Core;
+use tokio_rustls::ServerConfigExt;
 
 use git;
 use util::{self, get_repo_path};
-pub use api::{self, nll_dashboard, dashboard, data, days, graph, info, CommitResponse, ServerResult};
+pub use api::{self, nll_dashboard, dashboard, data, days, graph, info, regressions, CommitResponse, ServerResult};
 use collector::{Date, Run, version_supports_incremental};
 use load::{CommitData, InputData};
 use antidote::RwLock;
+use datastore::{migrate_from_fs, DataStore, SqliteDataStore};
+use push_event::PushEvent;
+use notifier::{Notifier, ReloadReport};
 
 /// Data associated with a specific date
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -121,6 +133,66 @@ pub fn handle_nll_dashboard(
     Ok(nll_dashboard::Response { commit: commit.commit.sha.clone(), points })
 }
 
+/// Renders the latest commit's benchmark values in the Prometheus text
+/// exposition format (version 0.0.4), so that external monitoring/alerting
+/// can scrape regressions without going through the dashboard frontend.
+pub fn handle_metrics(data: &InputData) -> String {
+    let commit = match data.data.values().last() {
+        Some(commit) => commit,
+        None => return String::new(),
+    };
+
+    let mut out = String::new();
+    out.push_str(
+        "# HELP rustc_perf_wall_time Wall-time measurement of a rustc-perf benchmark run, in seconds.\n",
+    );
+    out.push_str("# TYPE rustc_perf_wall_time gauge\n");
+
+    for benchmark in commit.benchmarks.values().filter_map(|v| v.as_ref().ok()) {
+        for run in &benchmark.runs {
+            let value = match run.get_stat("wall-time") {
+                Some(value) => value,
+                None => continue,
+            };
+            let profile = if run.release {
+                "opt"
+            } else if run.check {
+                "check"
+            } else {
+                "debug"
+            };
+            let cache = if run.is_clean() {
+                "clean"
+            } else if run.is_base_incr() {
+                "base-incr"
+            } else if run.is_clean_incr() {
+                "clean-incr"
+            } else if run.is_println_incr() {
+                "println-incr"
+            } else {
+                // Not one of the cache scenarios we expose as metrics.
+                continue;
+            };
+            out.push_str(&format!(
+                "rustc_perf_wall_time{{benchmark=\"{}\",profile=\"{}\",cache=\"{}\",stat=\"wall-time\"}} {}\n",
+                escape_label_value(&benchmark.name),
+                profile,
+                cache,
+                value,
+            ));
+        }
+    }
+    out
+}
+
+/// Escapes a Prometheus label value per the text exposition format: backslash,
+/// double-quote, and newline must be backslash-escaped.
+fn escape_label_value(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 pub fn handle_info(data: &InputData) -> info::Response {
     info::Response {
         crates: data.crate_list.clone(),
@@ -263,7 +335,11 @@ pub fn handle_dashboard(data: &InputData) -> dashboard::Response {
     }
 }
 
-pub fn handle_graph(body: graph::Request, data: &InputData) -> ServerResult<graph::Response> {
+pub fn handle_graph(
+    body: graph::Request,
+    data: &InputData,
+    store: &DataStore,
+) -> ServerResult<graph::Response> {
     let out = handle_data(
         data::Request {
             start: body.start.clone(),
@@ -271,6 +347,7 @@ pub fn handle_graph(body: graph::Request, data: &InputData) -> ServerResult<grap
             stat: body.stat.clone(),
         },
         data,
+        store,
     )?.0;
 
     // crate list * 3 because we have check, debug, and opt variants.
@@ -392,25 +469,89 @@ pub fn handle_graph(body: graph::Request, data: &InputData) -> ServerResult<grap
     })
 }
 
-pub fn handle_data(body: data::Request, data: &InputData) -> ServerResult<data::Response> {
+/// Builds the same per-commit shape [`DateData::for_day`] produces, but from
+/// rows already filtered to a single stat by [`DataStore::stat_in_range`]
+/// rather than by scanning every run of every benchmark in `commit`.
+fn date_data_from_store_rows(
+    sha: String,
+    date: Date,
+    rows: Vec<(String, Run, f64)>,
+) -> DateData {
+    let mut data: HashMap<String, Vec<(String, Run, f64)>> = HashMap::new();
+    for (benchmark, run, value) in rows {
+        let suffix = if run.release {
+            "-opt"
+        } else if run.check {
+            "-check"
+        } else {
+            "-debug"
+        };
+        data.entry(benchmark + suffix)
+            .or_insert_with(Vec::new)
+            .push((run.name(), run.clone(), value));
+    }
+    DateData { date, commit: sha, data }
+}
+
+/// Resolves `body`'s logical start/end into concrete commits via `data` (the
+/// in-memory corpus still holds the fuzzy-date/"HEAD" resolution logic), but
+/// fetches the actual stat values from `store` with a query scoped to just
+/// this date range and stat, instead of re-deriving every benchmark's full
+/// stat map for every commit in range.
+pub fn handle_data(
+    body: data::Request,
+    data: &InputData,
+    store: &DataStore,
+) -> ServerResult<data::Response> {
     debug!(
         "handle_data: start = {:?}, end = {:?}",
         body.start, body.end
     );
     let range = util::data_range(&data, &body.start, &body.end)?;
-    let mut result = range
-        .into_iter()
-        .map(|(_, day)| day)
-        .map(|day| DateData::for_day(day, &body.stat))
-        .collect::<Vec<_>>();
-
-    if result.is_empty() {
+    if range.is_empty() {
         return Err(format!(
             "empty range: {:?} to {:?} contained no commits",
             body.start, body.end
         ));
     }
 
+    let start_sha = range.first().unwrap().1.commit.sha.clone();
+    let end_sha = range.last().unwrap().1.commit.sha.clone();
+
+    let mut result = match store.stat_in_range(&start_sha, &end_sha, &body.stat) {
+        Ok(rows) => {
+            let mut by_commit: HashMap<String, Vec<(String, Run, f64)>> = HashMap::new();
+            for (sha, benchmark, run, value) in rows {
+                // Same unit conversion as `DateData::for_day`.
+                let value = if body.stat == "cpu-clock" { value / 1000.0 } else { value };
+                by_commit
+                    .entry(sha)
+                    .or_insert_with(Vec::new)
+                    .push((benchmark, run, value));
+            }
+            range
+                .into_iter()
+                .map(|(_, day)| {
+                    let sha = day.commit.sha.clone();
+                    let rows = by_commit.remove(&sha).unwrap_or_default();
+                    date_data_from_store_rows(sha, day.commit.date, rows)
+                })
+                .collect::<Vec<_>>()
+        }
+        Err(err) => {
+            // The store should always have these commits by the time they're
+            // queryable (`migrate_from_fs` runs synchronously before a push
+            // is merged into `data`); fall back to the in-memory scan rather
+            // than fail the request if that invariant is ever violated.
+            error!("handle_data: falling back to in-memory scan, store query failed: {:?}", err);
+            range
+                .into_iter()
+                .map(|(_, day)| day)
+                .map(|day| DateData::for_day(day, &body.stat))
+                .collect::<Vec<_>>()
+        }
+    };
+
     // Return everything from the first non-empty data to the last non-empty data.
     // Data may contain "holes" of empty data.
     let first_idx = result
@@ -425,6 +566,190 @@ pub fn handle_data(body: data::Request, data: &InputData) -> ServerResult<data::
     Ok(data::Response(result))
 }
 
+/// Default length of the trailing window of prior values used to establish
+/// a series' baseline before the point currently being tested.
+const REGRESSION_WINDOW: usize = 20;
+/// Minimum percent change (in either direction) before a point is even
+/// considered; filters out noise that never amounted to anything.
+const REGRESSION_PERCENT_THRESHOLD: f64 = 1.0;
+/// Minimum number of robust standard deviations (`1.4826 * MAD`) a point
+/// must deviate by, on top of the percent threshold, to be flagged.
+const REGRESSION_SIGMA_THRESHOLD: f64 = 3.0;
+
+/// Walks the same per-series data produced for `handle_graph` and flags
+/// commits that introduced a statistically meaningful change, so CI can
+/// alert on regressions without a human eyeballing graphs.
+///
+/// For each benchmark+profile+cache series, points are processed in commit
+/// order against a sliding window of the previous [`REGRESSION_WINDOW`]
+/// values (shorter at the start of the series). The window's median `M` and
+/// median absolute deviation are used to derive a robust standard deviation
+/// estimate `sigma = 1.4826 * MAD`. A point is flagged only when both the
+/// percent change from `M` exceeds [`REGRESSION_PERCENT_THRESHOLD`] and the
+/// absolute deviation from `M` exceeds `REGRESSION_SIGMA_THRESHOLD * sigma`,
+/// which suppresses the run-to-run noise that plagues wall-time
+/// measurements while still catching real jumps. Windows whose MAD is zero
+/// fall back to the percent threshold alone.
+pub fn handle_regressions(
+    body: regressions::Request,
+    data: &InputData,
+    store: &DataStore,
+) -> ServerResult<regressions::Response> {
+    let out = handle_data(
+        data::Request {
+            start: body.start.clone(),
+            end: body.end.clone(),
+            stat: body.stat.clone(),
+        },
+        data,
+        store,
+    )?.0;
+
+    let mut series: HashMap<(String, &'static str, &'static str), Vec<(String, f64)>> =
+        HashMap::new();
+    for date_data in &out {
+        for (key, runs) in &date_data.data {
+            let (benchmark, profile) = split_benchmark_profile(key);
+            for &(_, ref run, value) in runs {
+                let cache = if run.is_clean() {
+                    "clean"
+                } else if run.is_base_incr() {
+                    "base-incr"
+                } else if run.is_clean_incr() {
+                    "clean-incr"
+                } else if run.is_println_incr() {
+                    "println-incr"
+                } else {
+                    continue;
+                };
+                series
+                    .entry((benchmark.clone(), profile, cache))
+                    .or_insert_with(Vec::new)
+                    .push((date_data.commit.clone(), value));
+            }
+        }
+    }
+
+    let mut regressions = Vec::new();
+    for ((benchmark, profile, cache), points) in series {
+        for i in 1..points.len() {
+            let window = &points[i.saturating_sub(REGRESSION_WINDOW)..i];
+            let window_values = window.iter().map(|&(_, v)| v).collect::<Vec<_>>();
+            let median = median(&window_values);
+            if median == 0.0 {
+                continue;
+            }
+            let mad = median_absolute_deviation(&window_values, median);
+            let sigma = 1.4826 * mad;
+
+            let (ref commit, value) = points[i];
+            let (ref prev_commit, _) = points[i - 1];
+            let percent_change = (value - median) / median * 100.0;
+            let deviates_enough = if mad == 0.0 {
+                true
+            } else {
+                (value - median).abs() > REGRESSION_SIGMA_THRESHOLD * sigma
+            };
+
+            if percent_change.abs() > REGRESSION_PERCENT_THRESHOLD && deviates_enough {
+                regressions.push(regressions::Regression {
+                    benchmark: benchmark.clone(),
+                    profile: profile.to_string(),
+                    cache: cache.to_string(),
+                    commit: commit.clone(),
+                    prev_commit: prev_commit.clone(),
+                    percent_change,
+                    sigma,
+                });
+            }
+        }
+    }
+
+    regressions.sort_by(|a, b| {
+        b.percent_change
+            .abs()
+            .partial_cmp(&a.percent_change.abs())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    Ok(regressions::Response { regressions })
+}
+
+/// Splits a `DateData` key (`"{benchmark}-opt"`/`"-check"`/`"-debug"`, as
+/// produced by [`DateData::for_day`]) back into the benchmark name and
+/// profile.
+fn split_benchmark_profile(key: &str) -> (String, &'static str) {
+    for &(suffix, profile) in &[("-opt", "opt"), ("-check", "check"), ("-debug", "debug")] {
+        if key.ends_with(suffix) {
+            return (key[..key.len() - suffix.len()].to_string(), profile);
+        }
+    }
+    (key.to_string(), "unknown")
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn median_absolute_deviation(values: &[f64], median_value: f64) -> f64 {
+    let deviations = values
+        .iter()
+        .map(|v| (v - median_value).abs())
+        .collect::<Vec<_>>();
+    median(&deviations)
+}
+
+#[cfg(test)]
+mod regression_math_tests {
+    use super::{median, median_absolute_deviation};
+
+    #[test]
+    fn median_of_odd_length_window() {
+        assert_eq!(median(&[1.0, 2.0, 3.0, 4.0, 5.0]), 3.0);
+    }
+
+    #[test]
+    fn median_of_even_length_window() {
+        assert_eq!(median(&[1.0, 2.0, 3.0, 4.0]), 2.5);
+    }
+
+    #[test]
+    fn median_of_single_value_window() {
+        // The shortest window `handle_regressions` ever builds, at the very
+        // start of a series.
+        assert_eq!(median(&[10.0]), 10.0);
+    }
+
+    #[test]
+    fn median_ignores_input_order() {
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0, 5.0]), 3.0);
+    }
+
+    #[test]
+    fn mad_of_varying_window() {
+        let values = [10.0, 12.0, 8.0, 10.0, 11.0];
+        let median_value = median(&values);
+        assert_eq!(median_value, 10.0);
+        assert_eq!(median_absolute_deviation(&values, median_value), 1.0);
+    }
+
+    #[test]
+    fn mad_is_zero_for_a_constant_window() {
+        // `handle_regressions` falls back to the percent-change threshold
+        // alone when this is zero, since a zero sigma would otherwise flag
+        // any nonzero deviation.
+        let values = [5.0, 5.0, 5.0];
+        assert_eq!(median_absolute_deviation(&values, median(&values)), 0.0);
+    }
+}
+
 pub fn handle_days(body: days::Request, data: &InputData) -> ServerResult<days::Response> {
     let a = util::find_commit(data, &body.start, true)?;
     let b = util::find_commit(data, &body.end, false)?;
@@ -434,6 +759,107 @@ pub fn handle_days(body: days::Request, data: &InputData) -> ServerResult<days::
     })
 }
 
+/// Maximum number of scenario rows rendered by [`render_days_markdown`]; the
+/// rest are dropped after sorting so the table stays small enough to paste
+/// directly into a PR/issue comment.
+const MAX_MARKDOWN_ROWS: usize = 100;
+
+/// Renders a Markdown table comparing two commits, ready to paste into a
+/// GitHub PR or issue comment. One row per benchmark+profile+cache scenario,
+/// sorted by descending magnitude of percent change. Scenarios present in
+/// only one of the two commits are shown with an explicit "new"/"removed"
+/// marker instead of being silently dropped.
+pub fn handle_days_report(body: days::Request, data: &InputData) -> ServerResult<String> {
+    let a = util::find_commit(data, &body.start, true)?;
+    let b = util::find_commit(data, &body.end, false)?;
+    Ok(render_days_markdown(
+        &DateData::for_day(a.1, &body.stat),
+        &DateData::for_day(b.1, &body.stat),
+    ))
+}
+
+fn render_days_markdown(a: &DateData, b: &DateData) -> String {
+    struct Row {
+        scenario: String,
+        start: Option<f64>,
+        end: Option<f64>,
+    }
+
+    let mut scenarios: HashMap<String, Row> = HashMap::new();
+    for (benchmark, runs) in &a.data {
+        for &(ref name, _, value) in runs {
+            let scenario = format!("{}-{}", benchmark, name);
+            scenarios
+                .entry(scenario.clone())
+                .or_insert_with(|| Row { scenario, start: None, end: None })
+                .start = Some(value);
+        }
+    }
+    for (benchmark, runs) in &b.data {
+        for &(ref name, _, value) in runs {
+            let scenario = format!("{}-{}", benchmark, name);
+            scenarios
+                .entry(scenario.clone())
+                .or_insert_with(|| Row { scenario, start: None, end: None })
+                .end = Some(value);
+        }
+    }
+
+    // Numeric rows (present in both commits) are sorted by descending
+    // magnitude of percent change and take priority over new/removed rows,
+    // which have no magnitude to rank by; otherwise a diff with more than
+    // `MAX_MARKDOWN_ROWS` newly-added/removed scenarios would push every
+    // real regression out of the truncated table.
+    let (mut numeric, mut other): (Vec<Row>, Vec<Row>) = scenarios
+        .into_iter()
+        .map(|(_, row)| row)
+        .partition(|r| r.start.is_some() && r.end.is_some());
+    numeric.sort_by(|r1, r2| {
+        let pct = |r: &Row| match (r.start, r.end) {
+            (Some(start), Some(end)) if start != 0.0 => ((end - start) / start * 100.0).abs(),
+            // Can't divide by zero, but the value still moved; rank it
+            // above every ordinary percent change.
+            _ => ::std::f64::MAX,
+        };
+        pct(r2).partial_cmp(&pct(r1)).unwrap_or(Ordering::Equal)
+    });
+    other.sort_by(|r1, r2| r1.scenario.cmp(&r2.scenario));
+    let mut rows = numeric;
+    rows.extend(other);
+    rows.truncate(MAX_MARKDOWN_ROWS);
+
+    let mut out = String::new();
+    out.push_str(&format!("Comparing `{}` to `{}`\n\n", a.commit, b.commit));
+    out.push_str("| scenario | start | end | delta | % |\n");
+    out.push_str("|---|---|---|---|---|\n");
+    for row in rows {
+        match (row.start, row.end) {
+            (Some(start), Some(end)) => {
+                let delta = end - start;
+                let percent = if start != 0.0 { delta / start * 100.0 } else { 0.0 };
+                out.push_str(&format!(
+                    "| {} | {:.2} | {:.2} | {:+.2} | {:+.2}% |\n",
+                    row.scenario, start, end, delta, percent
+                ));
+            }
+            (None, Some(end)) => {
+                out.push_str(&format!(
+                    "| {} | - | {:.2} | new | new |\n",
+                    row.scenario, end
+                ));
+            }
+            (Some(start), None) => {
+                out.push_str(&format!(
+                    "| {} | {:.2} | - | removed | removed |\n",
+                    row.scenario, start
+                ));
+            }
+            (None, None) => {}
+        }
+    }
+    out
+}
+
 pub fn handle_date_commit(date: Date) -> CommitResponse {
     let commits = ::rust_sysroot::get_commits(::rust_sysroot::EPOCH_COMMIT, "master").unwrap();
 
@@ -455,10 +881,471 @@ pub fn handle_pr_commit(pr: u64) -> CommitResponse {
     }
 }
 
+/// Re-reads the entire on-disk corpus and swaps it into `data`, indexing any
+/// newly-seen commits into `store` along the way.
+fn reload_from_filesystem(
+    store: &DataStore,
+    data: &RwLock<InputData>,
+) -> Result<serde_json::Value, Error> {
+    let repo_path = get_repo_path()?;
+
+    git::update_repo(&repo_path)?;
+
+    info!("updating from filesystem...");
+    let new_data = InputData::from_fs(&repo_path)?;
+    debug!("last date = {:?}", new_data.last_date);
+
+    // Index the freshly-loaded commits in the persistent store so that
+    // future pushes can eventually avoid the full filesystem re-read above;
+    // already-indexed commits are skipped.
+    migrate_from_fs(store, &new_data.data.values().cloned().collect::<Vec<_>>())?;
+
+    *data.write() = new_data;
+
+    Ok(serde_json::to_value("Successfully updated from filesystem")?)
+}
+
+/// The only ref this server indexes commits from; pushes to anything else
+/// (PR branches, other release branches, ...) are acknowledged but ignored.
+const TRACKED_REF: &str = "refs/heads/master";
+
+/// Applies a parsed push event, migrating/merging only the commits it names
+/// instead of the whole corpus. Falls back to [`reload_from_filesystem`] for
+/// anything this module can't interpret incrementally (`PushEvent::Other`,
+/// or a push with an empty commit list). Pushes to any ref but
+/// [`TRACKED_REF`] are ignored outright.
+fn handle_push_event(
+    event: PushEvent,
+    store: &DataStore,
+    data: &RwLock<InputData>,
+) -> Result<serde_json::Value, Error> {
+    let (repo_name, git_ref, tip, commits) = match event {
+        PushEvent::Push { repo_name, git_ref, tip, commits } => (repo_name, git_ref, tip, commits),
+        PushEvent::Other => return reload_from_filesystem(store, data),
+    };
+    if git_ref != TRACKED_REF {
+        debug!("onpush: ignoring push to {} (tracking {} only)", git_ref, TRACKED_REF);
+        return Ok(serde_json::to_value(format!(
+            "Ignored push to {}; only {} is tracked",
+            git_ref, TRACKED_REF
+        ))?);
+    }
+    if commits.is_empty() {
+        return reload_from_filesystem(store, data);
+    }
+
+    debug!(
+        "onpush: incrementally updating {} commit(s) pushed to {} (tip {})",
+        commits.len(),
+        repo_name,
+        tip
+    );
+
+    let repo_path = get_repo_path()?;
+    git::update_repo(&repo_path)?;
+    let new_commits = InputData::from_fs_partial(&repo_path, &commits)?;
+    migrate_from_fs(store, &new_commits)?;
+
+    let mut data = data.write();
+    for commit in new_commits {
+        data.data.insert(commit.commit.clone(), commit);
+    }
+
+    Ok(serde_json::to_value(format!(
+        "Updated {} commit(s) from push to {}",
+        commits.len(),
+        repo_name
+    ))?)
+}
+
+fn sse_content_type() -> ContentType {
+    ContentType("text/event-stream".parse().unwrap())
+}
+
+/// Pushes one Server-Sent Event carrying `message` onto `tx`. Returns `Err`
+/// once the client has gone away (the receiving end of the body was
+/// dropped), so the caller can stop doing further work for this request.
+fn push_sse_event(tx: hyper::body::Sender, message: &str) -> Result<hyper::body::Sender, ()> {
+    tx.send(Ok(Chunk::from(format!("data: {}\n\n", message))))
+        .wait()
+        .map_err(|_| ())
+}
+
+/// Runs the same update `handle_push` performs, but streams a progress
+/// event after each step instead of leaving the client to block until the
+/// whole thing finishes. The update itself always runs to completion
+/// regardless of whether any of those progress events are actually
+/// delivered — a caller that stops reading the SSE body (or never reads it
+/// at all) must not prevent the reload from happening.
+fn stream_push_update(
+    body: Vec<u8>,
+    store: &DataStore,
+    data: &RwLock<InputData>,
+    notifier: &Notifier,
+    pool: &CpuPool,
+    tx: hyper::body::Sender,
+) {
+    let started = Instant::now();
+    let event = PushEvent::parse(&body);
+    let commit = match event {
+        Ok(PushEvent::Push { ref tip, .. }) => tip.clone(),
+        _ => "unknown".to_string(),
+    };
+
+    // `tx` becomes `None` the first time a send fails (the client went
+    // away); later code only uses it to skip further sends, never to decide
+    // whether to run the update.
+    let mut tx = push_sse_event(tx, "starting update").ok();
+
+    let result = match event {
+        Ok(event) => handle_push_event(event, store, data),
+        Err(err) => {
+            if let Some(t) = tx.take() {
+                tx = push_sse_event(
+                    t,
+                    &format!("failed to parse push payload, falling back to full reload: {:?}", err),
+                ).ok();
+            }
+            reload_from_filesystem(store, data)
+        }
+    };
+
+    let message = match result {
+        Ok(_) => "done".to_string(),
+        Err(ref err) => format!("error: {:?}", err),
+    };
+    notifier.notify(pool, ReloadReport {
+        commit,
+        success: result.is_ok(),
+        duration_ms: duration_to_ms(started.elapsed()),
+    });
+    if let Some(tx) = tx {
+        let _ = push_sse_event(tx, &message);
+    }
+}
+
+/// Chunk size used when streaming a static file to the client.
+const STATIC_FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Extension -> MIME type table for the static file handler, checked in
+/// order; unrecognized extensions fall back to `application/octet-stream`.
+const STATIC_MIME_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("css", "text/css"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("ico", "image/x-icon"),
+    ("wasm", "application/wasm"),
+    ("txt", "text/plain; charset=utf-8"),
+];
+
+fn guess_content_type(path: &Path) -> ContentType {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mime = STATIC_MIME_TYPES
+        .iter()
+        .find(|&&(candidate, _)| candidate == ext)
+        .map(|&(_, mime)| mime)
+        .unwrap_or("application/octet-stream");
+    ContentType(mime.parse().unwrap())
+}
+
+/// Serves a static file under `site/static`, honoring `If-None-Match` /
+/// `If-Modified-Since` (via a weak ETag derived from size + mtime) and a
+/// single-range `Range: bytes=start-end` request. Large responses are
+/// streamed in `STATIC_FILE_CHUNK_SIZE` chunks rather than buffered whole
+/// into memory.
+fn serve_static_file(fs_path: String, req: &Request, pool: &CpuPool) -> <Server as Service>::Future {
+    check_http_method!(*req.method(), Get);
+
+    let metadata = match fs::metadata(&fs_path) {
+        Ok(metadata) => metadata,
+        Err(_) => {
+            return Box::new(futures::future::ok(
+                Response::new()
+                    .with_header(ContentType::html())
+                    .with_status(StatusCode::NotFound),
+            ));
+        }
+    };
+    let len = metadata.len();
+    let modified_secs = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let etag = EntityTag::weak(format!("{:x}-{:x}", len, modified_secs));
+    let last_modified = HttpDate::from(UNIX_EPOCH + Duration::from_secs(modified_secs));
+
+    // Per RFC 7232 §3.3, `If-Modified-Since` is ignored entirely when the
+    // request also carries `If-None-Match`; the two aren't OR'd together.
+    let not_modified = match req.headers().get::<IfNoneMatch>() {
+        Some(&IfNoneMatch::Any) => true,
+        Some(&IfNoneMatch::Items(ref tags)) => tags.iter().any(|t| t.weak_eq(&etag)),
+        None => req
+            .headers()
+            .get::<IfModifiedSince>()
+            .map_or(false, |&IfModifiedSince(ref since)| *since >= last_modified),
+    };
+
+    if not_modified {
+        return Box::new(futures::future::ok(
+            Response::new()
+                .with_status(StatusCode::NotModified)
+                .with_header(ETag(etag))
+                .with_header(LastModified(last_modified)),
+        ));
+    }
+
+    let requested_range = match req.headers().get::<Range>() {
+        Some(&Range::Bytes(ref ranges)) => ranges.first().cloned(),
+        _ => None,
+    };
+    let (start, end) = match requested_range {
+        Some(ByteRangeSpec::FromTo(start, end)) => (start, end.min(len.saturating_sub(1))),
+        Some(ByteRangeSpec::AllFrom(start)) => (start, len.saturating_sub(1)),
+        Some(ByteRangeSpec::Last(n)) => (len.saturating_sub(n.min(len)), len.saturating_sub(1)),
+        None => (0, len.saturating_sub(1)),
+    };
+    let is_range = requested_range.is_some();
+    if is_range && (len == 0 || start > end || start >= len) {
+        return Box::new(futures::future::ok(
+            Response::new()
+                .with_status(StatusCode::RangeNotSatisfiable)
+                .with_header(ContentRange(ContentRangeSpec::Bytes {
+                    range: None,
+                    instance_length: Some(len),
+                })),
+        ));
+    }
+
+    let content_length = if len == 0 { 0 } else { end - start + 1 };
+    let (tx, body) = hyper::Body::pair();
+    let mut response = Response::new()
+        .with_status(if is_range { StatusCode::PartialContent } else { StatusCode::Ok })
+        .with_header(guess_content_type(Path::new(&fs_path)))
+        .with_header(ETag(etag))
+        .with_header(LastModified(last_modified))
+        .with_header(ContentLength(content_length))
+        .with_body(body);
+    if is_range {
+        response = response.with_header(ContentRange(ContentRangeSpec::Bytes {
+            range: Some((start, end)),
+            instance_length: Some(len),
+        }));
+    }
+
+    self::stream_file_chunks(pool, fs_path, start, content_length, tx);
+
+    Box::new(futures::future::ok(response))
+}
+
+/// Reads `len` bytes starting at `start` from `path` on the worker pool,
+/// pushing each chunk into `tx` as it's read rather than buffering the
+/// whole file.
+fn stream_file_chunks(
+    pool: &CpuPool,
+    path: String,
+    start: u64,
+    len: u64,
+    tx: hyper::body::Sender,
+) {
+    pool.spawn_fn(move || -> Result<(), ()> {
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(()),
+        };
+        if file.seek(SeekFrom::Start(start)).is_err() {
+            return Ok(());
+        }
+
+        let mut tx = tx;
+        let mut remaining = len;
+        let mut buf = vec![0u8; STATIC_FILE_CHUNK_SIZE];
+        while remaining > 0 {
+            let to_read = remaining.min(STATIC_FILE_CHUNK_SIZE as u64) as usize;
+            match file.read(&mut buf[..to_read]) {
+                Ok(0) => break,
+                Ok(n) => {
+                    remaining -= n as u64;
+                    if tx.send(Ok(Chunk::from(buf[..n].to_vec()))).wait().is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }).forget();
+}
+
+/// Whether the client's `Accept` header requests the compact binary form
+/// (`application/msgpack`) rather than JSON.
+fn client_accepts_msgpack(req: &Request) -> bool {
+    req.headers()
+        .get::<Accept>()
+        .map_or(false, |accept| accept.iter().any(|qi| qi.item.subtype() == "msgpack"))
+}
+
+/// Whether the client's `Accept-Encoding` header allows a gzip-compressed body.
+fn client_accepts_gzip(req: &Request) -> bool {
+    req.headers()
+        .get::<AcceptEncoding>()
+        .map_or(false, |e| e.iter().any(|e| e.item == Encoding::Gzip))
+}
+
+/// Serializes `result` per content negotiation and wraps it in a `Response`:
+/// `application/msgpack` when `msgpack` is true, JSON otherwise; gzip-encoded
+/// when `gzip` is true. Shared by the GET and POST handlers so payloads for
+/// the large `handle_graph`/`handle_dashboard` responses can be shrunk the
+/// same way on either path.
+fn encode_response<S: Serialize>(result: &S, msgpack: bool, gzip: bool) -> Response {
+    let (content_type, body) = if msgpack {
+        (
+            ContentType("application/msgpack".parse().unwrap()),
+            rmp_serde::to_vec_named(result).unwrap(),
+        )
+    } else {
+        (ContentType::json(), serde_json::to_vec(result).unwrap())
+    };
+
+    let response = Response::new().with_header(content_type);
+    if gzip {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&body).unwrap();
+        let body = encoder.finish().unwrap();
+        response
+            .with_header(ContentEncoding(vec![Encoding::Gzip]))
+            .with_body(body)
+    } else {
+        response.with_body(body)
+    }
+}
+
 struct Server {
     data: Arc<RwLock<InputData>>,
+    store: Arc<DataStore>,
     pool: CpuPool,
     updating: Arc<AtomicBool>,
+    /// Pre-shared keys accepted on `/perf/onpush`; a push is authenticated if
+    /// its `X-Hub-Signature-256` matches HMAC-SHA256 of the body under any
+    /// one of these, so keys can be rotated without downtime.
+    push_psks: Arc<Vec<String>>,
+    notifier: Arc<Notifier>,
+}
+
+/// Converts a `Duration` to whole milliseconds, for `ReloadReport`.
+fn duration_to_ms(d: Duration) -> u64 {
+    d.as_secs() * 1000 + u64::from(d.subsec_nanos() / 1_000_000)
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Verifies the GitHub-style `X-Hub-Signature-256: sha256=<hex>` header
+/// against `HMAC-SHA256(psk, body)` for any configured pre-shared key. The
+/// comparison is constant-time (via `Mac::verify`); `body` must be the exact
+/// raw request bytes, since the MAC is computed over them before any JSON
+/// parsing happens.
+fn verify_push_signature(psks: &[String], body: &[u8], header: Option<&[u8]>) -> bool {
+    let header = match header.and_then(|h| str::from_utf8(h).ok()) {
+        Some(h) => h,
+        None => return false,
+    };
+    let hex_sig = match header.starts_with("sha256=") {
+        true => &header["sha256=".len()..],
+        false => return false,
+    };
+    let signature = match hex_decode(hex_sig) {
+        Some(sig) => sig,
+        None => return false,
+    };
+
+    psks.iter().any(|psk| {
+        HmacSha256::new_varkey(psk.as_bytes())
+            .map(|mut mac| {
+                mac.input(body);
+                mac.verify(&signature).is_ok()
+            })
+            .unwrap_or(false)
+    })
+}
+
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod push_signature_tests {
+    use super::{hex_decode, verify_push_signature};
+
+    const BODY: &[u8] = br#"{"ref":"refs/heads/master"}"#;
+    const PSK: &str = "testsecret";
+    // `echo -n "$BODY" | openssl dgst -sha256 -hmac "$PSK"`
+    const SIGNATURE: &str = "dcb2cfea04faf987e58e5887f7069dc5c5073bcc7fef8a58707e7b82eacb54a7";
+
+    fn header(sig: &str) -> String {
+        format!("sha256={}", sig)
+    }
+
+    #[test]
+    fn accepts_correct_signature() {
+        assert!(verify_push_signature(
+            &[PSK.to_string()],
+            BODY,
+            Some(header(SIGNATURE).as_bytes()),
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        assert!(!verify_push_signature(
+            &["not-the-right-key".to_string()],
+            BODY,
+            Some(header(SIGNATURE).as_bytes()),
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(!verify_push_signature(&[PSK.to_string()], BODY, None));
+    }
+
+    #[test]
+    fn rejects_malformed_header() {
+        // Missing the `sha256=` prefix entirely.
+        assert!(!verify_push_signature(
+            &[PSK.to_string()],
+            BODY,
+            Some(SIGNATURE.as_bytes()),
+        ));
+        // Odd-length hex can't decode to bytes.
+        assert!(!verify_push_signature(
+            &[PSK.to_string()],
+            BODY,
+            Some(header("abc").as_bytes()),
+        ));
+    }
+
+    #[test]
+    fn accepts_any_configured_psk_during_rotation() {
+        let psks = vec!["old-key".to_string(), PSK.to_string()];
+        assert!(verify_push_signature(&psks, BODY, Some(header(SIGNATURE).as_bytes())));
+    }
+
+    #[test]
+    fn hex_decode_accepts_valid_and_rejects_invalid_hex() {
+        assert_eq!(hex_decode("00ff"), Some(vec![0x00, 0xff]));
+        assert_eq!(hex_decode("0"), None);
+        assert_eq!(hex_decode("zz"), None);
+    }
 }
 
 macro_rules! check_http_method {
@@ -480,9 +1367,7 @@ impl Server {
         let data = self.data.clone();
         let data = data.read();
         let result = handler(&data);
-        let response = Response::new()
-            .with_header(ContentType::json())
-            .with_body(serde_json::to_string(&result).unwrap());
+        let response = encode_response(&result, client_accepts_msgpack(req), client_accepts_gzip(req));
         Box::new(futures::future::ok(response))
     }
 
@@ -495,9 +1380,7 @@ impl Server {
         let data = self.data.clone();
         let data = data.read();
         let result = handler(req, &data);
-        let response = Response::new()
-            .with_header(ContentType::json())
-            .with_body(serde_json::to_string(&result).unwrap());
+        let response = encode_response(&result, client_accepts_msgpack(req), client_accepts_gzip(req));
         Box::new(futures::future::ok(response))
     }
 
@@ -516,9 +1399,7 @@ impl Server {
             // 10 kB
             return Box::new(futures::future::err(hyper::Error::TooLarge));
         }
-        let accepts_gzip = req.headers()
-            .get::<AcceptEncoding>()
-            .map_or(false, |e| e.iter().any(|e| e.item == Encoding::Gzip));
+        let accepts_gzip = client_accepts_gzip(&req);
         let data = self.data.clone();
         Box::new(self.pool.spawn_fn(move || {
             req.body()
@@ -544,23 +1425,9 @@ impl Server {
                     let result = handler(body, &data);
                     match result {
                         Ok(result) => {
-                            let mut response = Response::new()
-                                .with_header(ContentType::octet_stream())
-                                .with_header(CacheControl(vec![
-                                    CacheDirective::NoCache,
-                                    CacheDirective::NoStore,
-                                ]));
-                            let body = rmp_serde::to_vec_named(&result).unwrap();
-                            if accepts_gzip {
-                                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
-                                encoder.write_all(&*body).unwrap();
-                                let body = encoder.finish().unwrap();
-                                response
-                                    .with_header(ContentEncoding(vec![Encoding::Gzip]))
-                                    .with_body(body)
-                            } else {
-                                response.with_body(body)
-                            }
+                            encode_response(&result, true, accepts_gzip).with_header(CacheControl(
+                                vec![CacheDirective::NoCache, CacheDirective::NoStore],
+                            ))
                         }
                         Err(err) => Response::new()
                             .with_status(StatusCode::InternalServerError)
@@ -575,64 +1442,272 @@ impl Server {
         }))
     }
 
-    fn handle_push(&self, _req: Request) -> <Self as Service>::Future {
-        // set to updating
-        let was_updating = self.updating
-            .compare_and_swap(false, true, AtomicOrdering::AcqRel);
+    /// Like [`Server::handle_post`], but also passes `&self.store` to
+    /// `handler`, for the endpoints (`/perf/data`, `/perf/graph`,
+    /// `/perf/regressions`) that pull stat values out of the SQL index
+    /// instead of scanning the in-memory corpus.
+    fn handle_post_with_store<'de, F, D, S>(&self, req: Request, handler: F) -> <Server as Service>::Future
+    where
+        F: FnOnce(D, &InputData, &DataStore) -> ServerResult<S> + Send + 'static,
+        D: DeserializeOwned,
+        S: Serialize,
+    {
+        check_http_method!(*req.method(), Post);
+        let length = req.headers()
+            .get::<ContentLength>()
+            .expect("content-length to exist")
+            .0;
+        if length > 10_000 {
+            // 10 kB
+            return Box::new(futures::future::err(hyper::Error::TooLarge));
+        }
+        let accepts_gzip = client_accepts_gzip(&req);
+        let data = self.data.clone();
+        let store = self.store.clone();
+        Box::new(self.pool.spawn_fn(move || {
+            req.body()
+                .fold(Vec::new(), |mut acc, chunk| {
+                    acc.extend_from_slice(&*chunk);
+                    futures::future::ok::<_, <Self as Service>::Error>(acc)
+                })
+                .map(move |body| {
+                    let data = data.read();
+                    let body: D = match serde_json::from_slice(&body) {
+                        Ok(d) => d,
+                        Err(err) => {
+                            error!(
+                                "failed to deserialize request {}: {:?}",
+                                String::from_utf8_lossy(&body),
+                                err
+                            );
+                            return Response::new()
+                                .with_header(ContentType::plaintext())
+                                .with_body(format!("Failed to deserialize request; {:?}", err));
+                        }
+                    };
+                    let result = handler(body, &data, &*store);
+                    match result {
+                        Ok(result) => {
+                            encode_response(&result, true, accepts_gzip).with_header(CacheControl(
+                                vec![CacheDirective::NoCache, CacheDirective::NoStore],
+                            ))
+                        }
+                        Err(err) => Response::new()
+                            .with_status(StatusCode::InternalServerError)
+                            .with_header(ContentType::plaintext())
+                            .with_header(CacheControl(vec![
+                                CacheDirective::NoCache,
+                                CacheDirective::NoStore,
+                            ]))
+                            .with_body(err),
+                    }
+                })
+        }))
+    }
 
-        if was_updating {
-            return Box::new(futures::future::ok(
-                Response::new()
-                    .with_body(format!("Already updating!"))
-                    .with_status(StatusCode::Ok)
-                    .with_header(ContentType(mime::TEXT_PLAIN_UTF_8)),
-            ));
+    fn handle_days_report_req(&self, req: Request) -> <Server as Service>::Future {
+        check_http_method!(*req.method(), Post);
+        let length = req.headers()
+            .get::<ContentLength>()
+            .expect("content-length to exist")
+            .0;
+        if length > 10_000 {
+            // 10 kB
+            return Box::new(futures::future::err(hyper::Error::TooLarge));
         }
+        let data = self.data.clone();
+        Box::new(self.pool.spawn_fn(move || {
+            req.body()
+                .fold(Vec::new(), |mut acc, chunk| {
+                    acc.extend_from_slice(&*chunk);
+                    futures::future::ok::<_, <Self as Service>::Error>(acc)
+                })
+                .map(move |body| {
+                    let data = data.read();
+                    let body: days::Request = match serde_json::from_slice(&body) {
+                        Ok(d) => d,
+                        Err(err) => {
+                            return Response::new()
+                                .with_header(ContentType::plaintext())
+                                .with_body(format!("Failed to deserialize request; {:?}", err));
+                        }
+                    };
+                    match handle_days_report(body, &data) {
+                        Ok(report) => Response::new()
+                            .with_header(ContentType::plaintext())
+                            .with_body(report),
+                        Err(err) => Response::new()
+                            .with_status(StatusCode::InternalServerError)
+                            .with_header(ContentType::plaintext())
+                            .with_body(err),
+                    }
+                })
+        }))
+    }
 
-        // FIXME we are throwing everything away and starting again. It would be
-        // better to read just the added files. These should be available in the
-        // body of the request.
+    fn handle_metrics_req(&self, req: &Request) -> <Server as Service>::Future {
+        check_http_method!(*req.method(), Get);
+        let data = self.data.clone();
+        let data = data.read();
+        let body = handle_metrics(&data);
+        let content_type = "text/plain; version=0.0.4".parse().unwrap();
+        let response = Response::new()
+            .with_header(ContentType(content_type))
+            .with_body(body);
+        Box::new(futures::future::ok(response))
+    }
 
-        debug!("received onpush hook");
+    fn handle_push(&self, req: Request) -> <Self as Service>::Future {
+        check_http_method!(*req.method(), Post);
+        let length = req.headers()
+            .get::<ContentLength>()
+            .expect("content-length to exist")
+            .0;
+        if length > 2_000_000 {
+            // 2 MB; comfortably above a real GitHub push payload, but still
+            // bounded ahead of the signature check below.
+            return Box::new(futures::future::err(hyper::Error::TooLarge));
+        }
 
+        // The signature is computed over the exact raw body bytes, so it
+        // must be captured before any JSON parsing happens.
+        let signature = req.headers()
+            .get_raw("X-Hub-Signature-256")
+            .and_then(|raw| raw.one())
+            .map(|bytes| bytes.to_vec());
+        let psks = self.push_psks.clone();
         let rwlock = self.data.clone();
+        let store = self.store.clone();
         let updating = self.updating.clone();
-        let response = self.pool.spawn_fn(move || -> Result<serde_json::Value, Error> {
-            let repo_path = get_repo_path()?;
+        let notifier = self.notifier.clone();
+        let pool = self.pool.clone();
 
-            git::update_repo(&repo_path)?;
-
-            info!("updating from filesystem...");
-            let new_data = InputData::from_fs(&repo_path)?;
-            debug!("last date = {:?}", new_data.last_date);
-
-            // Retrieve the stored InputData from the request.
-            let mut data = rwlock.write();
+        Box::new(self.pool.spawn_fn(move || {
+            req.body()
+                .fold(Vec::new(), |mut acc, chunk| {
+                    acc.extend_from_slice(&*chunk);
+                    futures::future::ok::<_, <Self as Service>::Error>(acc)
+                })
+                .map(move |body| {
+                    if !verify_push_signature(&psks, &body, signature.as_ref().map(Vec::as_slice)) {
+                        return Response::new()
+                            .with_status(StatusCode::Unauthorized)
+                            .with_header(ContentType(mime::TEXT_PLAIN_UTF_8))
+                            .with_body("invalid or missing X-Hub-Signature-256".to_string());
+                    }
 
-            // Write the new data back into the request
-            *data = new_data;
+                    // set to updating
+                    let was_updating = updating.compare_and_swap(false, true, AtomicOrdering::AcqRel);
+                    if was_updating {
+                        return Response::new()
+                            .with_body(format!("Already updating!"))
+                            .with_status(StatusCode::Ok)
+                            .with_header(ContentType(mime::TEXT_PLAIN_UTF_8));
+                    }
 
-            updating.store(false, AtomicOrdering::Release);
+                    debug!("received onpush hook");
 
-            Ok(serde_json::to_value(
-                "Successfully updated from filesystem",
-            )?)
-        });
+                    let started = Instant::now();
+                    let event = PushEvent::parse(&body);
+                    let commit = match event {
+                        Ok(PushEvent::Push { ref tip, .. }) => tip.clone(),
+                        _ => "unknown".to_string(),
+                    };
 
-        let updating = self.updating.clone();
-        Box::new(
-            response
-                .map(|value| Response::new().with_body(serde_json::to_string(&value).unwrap()))
-                .or_else(move |err| {
+                    let result = match event {
+                        Ok(event) => handle_push_event(event, &*store, &rwlock),
+                        Err(err) => {
+                            error!(
+                                "failed to parse push payload, falling back to full reload: {:?}",
+                                err
+                            );
+                            reload_from_filesystem(&*store, &rwlock)
+                        }
+                    };
                     updating.store(false, AtomicOrdering::Release);
-                    futures::future::ok(
-                        Response::new()
+                    notifier.notify(&pool, ReloadReport {
+                        commit,
+                        success: result.is_ok(),
+                        duration_ms: duration_to_ms(started.elapsed()),
+                    });
+
+                    match result {
+                        Ok(value) => {
+                            Response::new().with_body(serde_json::to_string(&value).unwrap())
+                        }
+                        Err(err) => Response::new()
                             .with_body(format!("Internal Server Error: {:?}", err))
                             .with_status(StatusCode::InternalServerError)
                             .with_header(ContentType(mime::TEXT_PLAIN_UTF_8)),
-                    )
-                }),
-        )
+                    }
+                })
+        }))
+    }
+
+    /// Like `handle_push`, but streams progress as Server-Sent Events
+    /// instead of blocking until the whole update finishes. The `updating`
+    /// guard still rejects concurrent updates; the rejection is emitted as
+    /// the first (and only) streamed event rather than returned directly.
+    fn handle_push_stream(&self, req: Request) -> <Self as Service>::Future {
+        check_http_method!(*req.method(), Post);
+        let length = req.headers()
+            .get::<ContentLength>()
+            .expect("content-length to exist")
+            .0;
+        if length > 2_000_000 {
+            // 2 MB; comfortably above a real GitHub push payload, but still
+            // bounded ahead of the signature check below.
+            return Box::new(futures::future::err(hyper::Error::TooLarge));
+        }
+
+        let signature = req.headers()
+            .get_raw("X-Hub-Signature-256")
+            .and_then(|raw| raw.one())
+            .map(|bytes| bytes.to_vec());
+        let psks = self.push_psks.clone();
+        let rwlock = self.data.clone();
+        let store = self.store.clone();
+        let updating = self.updating.clone();
+        let notifier = self.notifier.clone();
+        let pool = self.pool.clone();
+
+        Box::new(self.pool.spawn_fn(move || {
+            req.body()
+                .fold(Vec::new(), |mut acc, chunk| {
+                    acc.extend_from_slice(&*chunk);
+                    futures::future::ok::<_, <Self as Service>::Error>(acc)
+                })
+                .map(move |body| {
+                    let (tx, body_stream) = hyper::Body::pair();
+
+                    if !verify_push_signature(&psks, &body, signature.as_ref().map(Vec::as_slice)) {
+                        let _ = push_sse_event(tx, "rejected: invalid or missing X-Hub-Signature-256");
+                        return Response::new()
+                            .with_status(StatusCode::Unauthorized)
+                            .with_header(sse_content_type())
+                            .with_body(body_stream);
+                    }
+
+                    let was_updating = updating.compare_and_swap(false, true, AtomicOrdering::AcqRel);
+                    if was_updating {
+                        let _ = push_sse_event(tx, "rejected: already updating");
+                        return Response::new()
+                            .with_header(sse_content_type())
+                            .with_body(body_stream);
+                    }
+
+                    let pool_for_notifier = pool.clone();
+                    pool.spawn_fn(move || -> Result<(), ()> {
+                        stream_push_update(body, &*store, &rwlock, &notifier, &pool_for_notifier, tx);
+                        updating.store(false, AtomicOrdering::Release);
+                        Ok(())
+                    }).forget();
+
+                    Response::new()
+                        .with_header(sse_content_type())
+                        .with_body(body_stream)
+                })
+        }))
     }
 }
 
@@ -667,20 +1742,18 @@ impl Service for Server {
         }
 
         if Path::new(&fs_path).is_file() {
-            return Box::new(self.pool.spawn_fn(move || {
-                let mut f = File::open(&fs_path).unwrap();
-                let mut source = Vec::new();
-                f.read_to_end(&mut source).unwrap();
-                futures::future::ok(Response::new().with_body(source))
-            }));
+            return serve_static_file(fs_path, &req, &self.pool);
         }
 
         match req.path() {
+            "/metrics" => self.handle_metrics_req(&req),
             "/perf/info" => self.handle_get(&req, handle_info),
             "/perf/dashboard" => self.handle_get(&req, handle_dashboard),
-            "/perf/data" => self.handle_post(req, handle_data),
-            "/perf/graph" => self.handle_post(req, handle_graph),
+            "/perf/data" => self.handle_post_with_store(req, handle_data),
+            "/perf/graph" => self.handle_post_with_store(req, handle_graph),
             "/perf/get" => self.handle_post(req, handle_days),
+            "/perf/get_report" => self.handle_days_report_req(req),
+            "/perf/regressions" => self.handle_post_with_store(req, handle_regressions),
             "/perf/nll_dashboard" => self.handle_post(req, handle_nll_dashboard),
             "/perf/pr_commit" => self.handle_get_req(&req, |req, _data| {
                 let res = req.query()
@@ -702,6 +1775,7 @@ impl Service for Server {
                 handle_date_commit(date.unwrap().1.parse().unwrap())
             }),
             "/perf/onpush" => self.handle_push(req),
+            "/perf/onpush_stream" => self.handle_push_stream(req),
             _ => Box::new(futures::future::ok(
                 Response::new()
                     .with_header(ContentType::html())
@@ -711,14 +1785,91 @@ impl Service for Server {
     }
 }
 
-pub fn start(data: InputData, port: u16) {
+/// Optional TLS termination settings for [`start`]. When `start` is given a
+/// `TlsConfig`, it serves HTTPS directly instead of plain HTTP, so deployments don't
+/// need a separate reverse proxy just to terminate TLS.
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+pub fn start(
+    data: InputData,
+    port: u16,
+    push_psks: Vec<String>,
+    tls: Option<TlsConfig>,
+    notifier_sinks: Vec<notifier::Sink>,
+) {
+    let store = SqliteDataStore::open(&get_repo_path().unwrap().join("perf-data.db"))
+        .expect("failed to open perf-data.db");
+    migrate_from_fs(&store, &data.data.values().cloned().collect::<Vec<_>>())
+        .expect("failed to migrate existing corpus into perf-data.db");
     let server = Arc::new(Server {
         data: Arc::new(RwLock::new(data)),
+        store: Arc::new(store),
         pool: CpuPool::new_num_cpus(),
         updating: Arc::new(AtomicBool::new(false)),
+        push_psks: Arc::new(push_psks),
+        notifier: Arc::new(Notifier::new(notifier_sinks)),
     });
     let mut server_address: SocketAddr = "0.0.0.0:2346".parse().unwrap();
     server_address.set_port(port);
-    let server = Http::new().bind(&server_address, move || Ok(server.clone()));
-    server.unwrap().run().unwrap();
+
+    match tls {
+        Some(tls) => {
+            let tls_config = load_tls_config(&tls.cert_path, &tls.key_path)
+                .expect("failed to load TLS certificate/key");
+            serve_tls(server, server_address, tls_config);
+        }
+        None => {
+            let server = Http::new().bind(&server_address, move || Ok(server.clone()));
+            server.unwrap().run().unwrap();
+        }
+    }
+}
+
+/// Builds a rustls server config from a PEM certificate chain and PEM
+/// (PKCS#8) private key.
+fn load_tls_config(cert_path: &Path, key_path: &Path) -> Result<Arc<rustls::ServerConfig>, Error> {
+    let certs = {
+        let mut reader = BufReader::new(File::open(cert_path)?);
+        rustls::internal::pemfile::certs(&mut reader)
+            .map_err(|_| format_err!("failed to parse certificate chain at {:?}", cert_path))?
+    };
+    let mut keys = {
+        let mut reader = BufReader::new(File::open(key_path)?);
+        rustls::internal::pemfile::pkcs8_private_keys(&mut reader)
+            .map_err(|_| format_err!("failed to parse private key at {:?}", key_path))?
+    };
+    let key = keys
+        .pop()
+        .ok_or_else(|| format_err!("no private key found at {:?}", key_path))?;
+
+    let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+    config.set_single_cert(certs, key)?;
+    Ok(Arc::new(config))
+}
+
+/// Runs the server over TLS: accepts plain TCP connections, performs the
+/// rustls handshake on each, then hands the resulting stream to hyper like
+/// `Http::bind` would for plaintext.
+fn serve_tls(server: Arc<Server>, addr: SocketAddr, tls_config: Arc<rustls::ServerConfig>) {
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let listener = TcpListener::bind(&addr, &handle).unwrap();
+    let http = Http::new();
+
+    let acceptor_handle = handle.clone();
+    let incoming = listener.incoming().for_each(move |(socket, _addr)| {
+        let server = server.clone();
+        let http = http.clone();
+        let handshake = tls_config
+            .accept_async(socket)
+            .map_err(|_| ())
+            .and_then(move |tls_stream| http.serve_connection(tls_stream, server).map_err(|_| ()));
+        acceptor_handle.spawn(handshake);
+        Ok(())
+    });
+
+    core.run(incoming).unwrap();
 }